@@ -5,6 +5,7 @@ use schemars::{schema_for, JsonSchema};
 use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, HashMap};
 use std::hash::Hash;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use thiserror::Error;
 use tracing::*;
@@ -31,9 +32,39 @@ struct Args {
     #[clap(short, long)]
     config: Option<String>,
 
+    /// A controller button/axis mapping file.
+    ///
+    /// Falls back to mapping fields embedded in `--config`, then to the
+    /// identity mapping, when not set.
+    #[clap(short, long)]
+    mapping: Option<String>,
+
     /// Loop sleep time
     #[clap(short, long, default_value = "50")]
     sleep_ms: u64,
+
+    /// Also publish each connected gamepad onto its own `<topic>/<gamepad_id>` key expression.
+    #[clap(long)]
+    publish_per_gamepad: bool,
+
+    /// A file of SDL2 `gamecontrollerdb`-style mapping lines, for controllers gilrs
+    /// doesn't recognize out of the box.
+    #[clap(long)]
+    sdl_mappings: Option<String>,
+
+    /// Only publish the combined input message when gamepad state actually changed,
+    /// instead of on every loop tick.
+    #[clap(long)]
+    publish_on_change: bool,
+
+    /// Axis delta that counts as a change when `--publish-on-change` is set.
+    #[clap(long, default_value = "0.01")]
+    change_epsilon: f32,
+
+    /// Maximum time between published frames when `--publish-on-change` is set, so
+    /// subscribers can detect staleness even with no new input.
+    #[clap(long, default_value = "1000")]
+    keep_alive_ms: u64,
 }
 
 #[tokio::main(worker_threads = 2)]
@@ -65,7 +96,7 @@ async fn main() -> anyhow::Result<()> {
         .into_arc();
 
     let gamepad_publisher = zenoh_session
-        .declare_publisher(args.topic)
+        .declare_publisher(args.topic.clone())
         .res()
         .await
         .map_err(HopperRemoteError::ZenohError)?;
@@ -76,26 +107,102 @@ async fn main() -> anyhow::Result<()> {
         serde_json::to_string(&schema).unwrap()
     );
 
+    // A dedicated `--mapping` file takes priority; otherwise fall back to mapping
+    // fields embedded in the `--config` file, so a single file can hold both the
+    // zenoh config and the button/axis remapping.
+    let map = if let Some(mapping_file) = &args.mapping {
+        let content = std::fs::read_to_string(mapping_file)?;
+        serde_yaml::from_str(&content)?
+    } else if let Some(conf_file) = &args.config {
+        let content = std::fs::read_to_string(conf_file)?;
+        serde_yaml::from_str(&content).unwrap_or_default()
+    } else {
+        Map::default()
+    };
+    info!("Using mapping {:?} / {:?}", args.mapping, args.config);
+
     info!("Starting gamepad reader");
 
     // gamepad
-    let mut gilrs = GilrsBuilder::new()
-        .with_default_filters(true)
-        .build()
-        .expect("Failed to get gilrs handle");
+    let mut gilrs_builder = GilrsBuilder::new().with_default_filters(true);
+    if let Some(sdl_mappings_file) = &args.sdl_mappings {
+        let sdl_mappings = std::fs::read_to_string(sdl_mappings_file)?;
+        info!("Loading SDL2 mappings from {:?}", sdl_mappings_file);
+        gilrs_builder = gilrs_builder.add_mappings(&sdl_mappings);
+    }
+    let gilrs = gilrs_builder.build().expect("Failed to get gilrs handle");
 
-    info!("{} gamepad(s) found", gilrs.gamepads().count());
+    {
+        let gilrs = gilrs.gamepads();
+        info!("{} gamepad(s) found", gilrs.count());
+    }
     for (_id, gamepad) in gilrs.gamepads() {
-        info!("{} is {:?}", gamepad.name(), gamepad.power_info());
+        info!(
+            "{} ({:?}) is {:?}, mapping source {:?}",
+            gamepad.name(),
+            gamepad.uuid(),
+            gamepad.power_info(),
+            gamepad.mapping_source()
+        );
+        if gamepad.mapping_source() == gilrs::MappingSource::None {
+            warn!(
+                "{} has no recognized mapping - add its SDL2 gamecontrollerdb line via --sdl-mappings",
+                gamepad.name()
+            );
+        }
     }
 
+    let gilrs = Arc::new(Mutex::new(gilrs));
+    let active_rumble_effects: Arc<Mutex<HashMap<usize, gilrs::ff::Effect>>> =
+        Arc::new(Mutex::new(HashMap::new()));
+
+    let rumble_topic = format!("{}/rumble", args.topic);
+    info!("Subscribing to rumble commands on {:?}", rumble_topic);
+    let rumble_subscriber = zenoh_session
+        .declare_subscriber(rumble_topic)
+        .res()
+        .await
+        .map_err(HopperRemoteError::ZenohError)?;
+
+    tokio::spawn({
+        let gilrs = gilrs.clone();
+        let active_rumble_effects = active_rumble_effects.clone();
+        async move {
+            while let Ok(sample) = rumble_subscriber.recv_async().await {
+                let command: RumbleCommand =
+                    match serde_json::from_slice(&sample.value.payload.contiguous()) {
+                        Ok(command) => command,
+                        Err(error) => {
+                            warn!("Failed to parse rumble command: {:?}", error);
+                            continue;
+                        }
+                    };
+                if let Err(error) = play_rumble(&gilrs, &active_rumble_effects, &command) {
+                    warn!("Failed to play rumble effect: {:?}", error);
+                }
+            }
+        }
+    });
+
+    let status_topic = format!("{}/status", args.topic);
+    info!("Publishing connection/power status on {:?}", status_topic);
+    let status_publisher = zenoh_session
+        .declare_publisher(status_topic)
+        .res()
+        .await
+        .map_err(HopperRemoteError::ZenohError)?;
+    let mut last_gamepad_status: HashMap<usize, (bool, Power)> = HashMap::new();
+
     let mut message_data = InputMessage {
         gamepads: HashMap::new(),
         time: std::time::SystemTime::now().into(),
     };
+    let mut per_gamepad_publishers = HashMap::new();
+    let mut last_published_gamepads: HashMap<usize, GamepadMessage> = HashMap::new();
+    let mut last_publish_time = std::time::Instant::now();
 
     loop {
-        while let Some(gilrs_event) = gilrs.next_event() {
+        while let Some(gilrs_event) = gilrs.lock().unwrap().next_event() {
             let gamepad_id: usize = gilrs_event.id.into();
             let gamepad_data = message_data.gamepads.entry(gamepad_id).or_default();
 
@@ -104,17 +211,26 @@ async fn main() -> anyhow::Result<()> {
                 gilrs::EventType::ButtonPressed(button, _) => {
                     *gamepad_data
                         .button_down_event_counter
-                        .entry(button.into())
+                        .entry(map.button(button))
                         .or_default() += 1;
                 }
                 gilrs::EventType::ButtonReleased(button, _) => {
                     *gamepad_data
                         .button_up_event_counter
-                        .entry(button.into())
+                        .entry(map.button(button))
                         .or_default() += 1;
                 }
                 gilrs::EventType::AxisChanged(axis, value, _) => {
-                    gamepad_data.axis_state.insert(axis.into(), value);
+                    let axis = map.axis(axis);
+                    // Deadzone describes the physical stick, so it's applied to the raw
+                    // value before the configurable scale, not after.
+                    let value =
+                        apply_deadzone_and_scale(value, map.deadzone(axis), map.axis_scale(axis));
+                    gamepad_data.axis_state.insert(axis, value);
+
+                    if let Some(axis_to_button) = map.axis_to_button(axis) {
+                        synthesize_axis_button(gamepad_data, axis, value, axis_to_button);
+                    }
                 }
                 gilrs::EventType::Connected => {
                     gamepad_data.connected = true;
@@ -125,39 +241,140 @@ async fn main() -> anyhow::Result<()> {
                     warn!(
                         "Gamepad {} - {} disconnected",
                         gamepad_id, gamepad_data.name
-                    )
+                    );
+                    if let Some(effect) = active_rumble_effects.lock().unwrap().remove(&gamepad_id)
+                    {
+                        let _ = effect.stop();
+                    }
                 }
                 _ => {}
             }
         }
 
-        if let Some((gamepad_id, gamepad)) = gilrs.gamepads().next() {
-            let gamepad_id: usize = gamepad_id.into();
+        // Refresh the polled (rather than event-driven) state for every connected gamepad,
+        // not just the first one, so a multi-controller setup stays fully reported.
+        let polled_gamepads: Vec<_> = {
+            let gilrs = gilrs.lock().unwrap();
+            gilrs
+                .gamepads()
+                .map(|(gamepad_id, gamepad)| {
+                    let gamepad_id: usize = gamepad_id.into();
+                    let buttons: Vec<_> = if gamepad.is_connected() {
+                        Button::all_gilrs_buttons()
+                            .iter()
+                            .map(|button| (map.button(*button), gamepad.is_pressed(*button)))
+                            // Buttons synthesized from a trigger axis own their `button_pressed`
+                            // entry; don't let the physical (usually unpressed) state of the
+                            // same button id overwrite what the axis handler just set.
+                            .filter(|(button, _)| !map.is_axis_driven_button(*button))
+                            .collect()
+                    } else {
+                        Vec::new()
+                    };
+                    (
+                        gamepad_id,
+                        gamepad.is_connected(),
+                        gamepad.name().to_string(),
+                        Power::from(gamepad.power_info()),
+                        buttons,
+                    )
+                })
+                .collect()
+        };
+
+        for (gamepad_id, connected, name, power, buttons) in polled_gamepads {
             let gamepad_data = message_data.gamepads.entry(gamepad_id).or_default();
+            gamepad_data.connected = connected;
+            gamepad_data.name = name;
+            gamepad_data.power = power;
+            for (button, pressed) in buttons {
+                gamepad_data.button_pressed.insert(button, pressed);
+            }
 
-            gamepad_data.connected = gamepad.is_connected();
-            gamepad_data.name = gamepad.name().to_string();
+            let last_status = last_gamepad_status.get(&gamepad_id).copied();
+            if last_status != Some((connected, power)) {
+                last_gamepad_status.insert(gamepad_id, (connected, power));
+                let status = StatusMessage {
+                    gamepad_id,
+                    connected,
+                    power,
+                    time: std::time::SystemTime::now().into(),
+                };
+                let json = serde_json::to_string(&status)?;
+                status_publisher
+                    .put(json)
+                    .res()
+                    .await
+                    .map_err(HopperRemoteError::ZenohError)?;
+            }
 
-            if gamepad.is_connected() {
-                for button in Button::all_gilrs_buttons() {
-                    gamepad_data
-                        .button_pressed
-                        .insert(Button::from(*button), gamepad.is_pressed(*button));
-                }
+            if args.publish_per_gamepad {
+                let key_expr = format!("{}/{}", args.topic, gamepad_id);
+                let publisher = match per_gamepad_publishers.entry(gamepad_id) {
+                    std::collections::hash_map::Entry::Occupied(entry) => entry.into_mut(),
+                    std::collections::hash_map::Entry::Vacant(entry) => {
+                        let publisher = zenoh_session
+                            .declare_publisher(key_expr)
+                            .res()
+                            .await
+                            .map_err(HopperRemoteError::ZenohError)?;
+                        entry.insert(publisher)
+                    }
+                };
+                let json = serde_json::to_string(gamepad_data)?;
+                publisher
+                    .put(json)
+                    .res()
+                    .await
+                    .map_err(HopperRemoteError::ZenohError)?;
+            }
+        }
 
-                // should we also get stick values here or use events?
-                // let x = gamepad.value(gilrs::Axis::LeftStickY);
-                // let x = if x.abs() > 0.2 { x } else { 0.0 };
+        // gilrs no longer lists a gamepad once it disconnects, so its disconnection comes
+        // from the event-driven `connected` flag instead.
+        for (gamepad_id, gamepad_data) in &message_data.gamepads {
+            if !gamepad_data.connected && matches!(last_gamepad_status.get(gamepad_id), Some((true, _))) {
+                let (connected, power) = (false, gamepad_data.power);
+                last_gamepad_status.insert(*gamepad_id, (connected, power));
+                let time = std::time::SystemTime::now().into();
+                let status = StatusMessage { gamepad_id: *gamepad_id, connected, power, time };
+                status_publisher
+                    .put(serde_json::to_string(&status)?)
+                    .res()
+                    .await
+                    .map_err(HopperRemoteError::ZenohError)?;
             }
         }
 
         message_data.time = std::time::SystemTime::now().into();
-        let json = serde_json::to_string(&message_data)?;
-        gamepad_publisher
-            .put(json)
-            .res()
-            .await
-            .map_err(HopperRemoteError::ZenohError)?;
+
+        let should_publish = if args.publish_on_change {
+            let changed = message_data.gamepads.len() != last_published_gamepads.len()
+                || message_data.gamepads.iter().any(|(id, gamepad)| {
+                    gamepad_changed(
+                        last_published_gamepads.get(id),
+                        gamepad,
+                        args.change_epsilon,
+                    )
+                });
+            let stale =
+                last_publish_time.elapsed() >= Duration::from_millis(args.keep_alive_ms);
+            changed || stale
+        } else {
+            true
+        };
+
+        if should_publish {
+            let json = serde_json::to_string(&message_data)?;
+            gamepad_publisher
+                .put(json)
+                .res()
+                .await
+                .map_err(HopperRemoteError::ZenohError)?;
+            last_published_gamepads = message_data.gamepads.clone();
+            last_publish_time = std::time::Instant::now();
+        }
+
         tokio::time::sleep(Duration::from_millis(args.sleep_ms)).await;
     }
 }
@@ -168,7 +385,7 @@ pub struct InputMessage {
     time: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize, Serialize, Default, JsonSchema)]
+#[derive(Debug, Deserialize, Serialize, Default, Clone, JsonSchema)]
 pub struct GamepadMessage {
     name: String,
     button_down_event_counter: BTreeMap<Button, usize>,
@@ -176,11 +393,112 @@ pub struct GamepadMessage {
     button_pressed: BTreeMap<Button, bool>,
     axis_state: BTreeMap<Axis, f32>,
     connected: bool,
+    power: Power,
     last_event_time: DateTime<Utc>,
+    /// Tracks which axis-to-button synthesized buttons are currently held down.
+    #[serde(skip)]
+    #[schemars(skip)]
+    synthetic_button_state: BTreeMap<Axis, bool>,
 }
 
 impl GamepadMessage {}
 
+/// Clamps a raw axis value to `0.0` inside `deadzone`, then applies `scale`.
+fn apply_deadzone_and_scale(value: f32, deadzone: f32, scale: f32) -> f32 {
+    let value = if value.abs() < deadzone { 0.0 } else { value };
+    value * scale
+}
+
+/// Applies axis-to-button synthesis: toggles `button_pressed` and the matching
+/// event counter when `value` crosses `axis_to_button`'s up/down thresholds.
+fn synthesize_axis_button(
+    gamepad_data: &mut GamepadMessage,
+    axis: Axis,
+    value: f32,
+    axis_to_button: &AxisToButton,
+) {
+    let pressed = gamepad_data.synthetic_button_state.entry(axis).or_default();
+    if !*pressed && value >= axis_to_button.up_threshold {
+        *pressed = true;
+        gamepad_data
+            .button_pressed
+            .insert(axis_to_button.button, true);
+        *gamepad_data
+            .button_down_event_counter
+            .entry(axis_to_button.button)
+            .or_default() += 1;
+    } else if *pressed && value <= axis_to_button.down_threshold {
+        *pressed = false;
+        gamepad_data
+            .button_pressed
+            .insert(axis_to_button.button, false);
+        *gamepad_data
+            .button_up_event_counter
+            .entry(axis_to_button.button)
+            .or_default() += 1;
+    }
+}
+
+/// True if `current` differs from `previous` enough to be worth publishing:
+/// any button event counter or pressed state changed, connection/power changed,
+/// or an axis moved by more than `axis_epsilon`.
+fn gamepad_changed(
+    previous: Option<&GamepadMessage>,
+    current: &GamepadMessage,
+    axis_epsilon: f32,
+) -> bool {
+    let Some(previous) = previous else {
+        return true;
+    };
+
+    if previous.connected != current.connected
+        || previous.power != current.power
+        || previous.button_down_event_counter != current.button_down_event_counter
+        || previous.button_up_event_counter != current.button_up_event_counter
+        || previous.button_pressed != current.button_pressed
+    {
+        return true;
+    }
+
+    current.axis_state.iter().any(|(axis, value)| {
+        let previous_value = previous.axis_state.get(axis).copied().unwrap_or(0.0);
+        (value - previous_value).abs() > axis_epsilon
+    })
+}
+
+/// Mirrors `gilrs::PowerInfo` so it can be serialized onto the wire.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Copy, Default, JsonSchema)]
+pub enum Power {
+    Wired,
+    Discharging(u8),
+    Charging(u8),
+    Charged,
+    #[default]
+    Unknown,
+}
+
+impl From<gilrs::PowerInfo> for Power {
+    fn from(value: gilrs::PowerInfo) -> Self {
+        match value {
+            gilrs::PowerInfo::Wired => Power::Wired,
+            gilrs::PowerInfo::Discharging(percentage) => Power::Discharging(percentage),
+            gilrs::PowerInfo::Charging(percentage) => Power::Charging(percentage),
+            gilrs::PowerInfo::Charged => Power::Charged,
+            gilrs::PowerInfo::Unknown => Power::Unknown,
+        }
+    }
+}
+
+/// A low-rate connection/power lifecycle update, published onto `<topic>/status`
+/// only when something actually changed.
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct StatusMessage {
+    gamepad_id: usize,
+    connected: bool,
+    power: Power,
+    time: DateTime<Utc>,
+}
+
 #[derive(
     Debug, Deserialize, Serialize, PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Copy, JsonSchema,
 )]
@@ -306,6 +624,95 @@ impl From<gilrs::ev::Axis> for Axis {
     }
 }
 
+/// Remaps gilrs buttons/axes onto [`Button`]/[`Axis`] and scales axis values.
+///
+/// Lets a PlayStation pad (which gilrs reports with East/South swapped
+/// relative to an Xbox pad) or a custom control scheme be normalized to a
+/// single naming without recompiling. Unmapped buttons/axes fall back to
+/// the default [`From`] conversion.
+#[derive(Debug, Default, Deserialize)]
+pub struct Map {
+    #[serde(default)]
+    button_map: HashMap<gilrs::Button, Button>,
+    #[serde(default)]
+    axis_map: HashMap<gilrs::Axis, Axis>,
+    #[serde(default)]
+    axis_value_map: HashMap<Axis, f32>,
+    /// Per-axis deadzone. Axes not listed here use [`DEFAULT_DEADZONE`].
+    #[serde(default)]
+    axis_deadzones: HashMap<Axis, f32>,
+    /// Trigger axes that should also be reported as synthetic buttons.
+    #[serde(default)]
+    axis_to_button: HashMap<Axis, AxisToButton>,
+}
+
+/// The deadzone gilrs itself defaults to for analog sticks.
+pub const DEFAULT_DEADZONE: f32 = 0.1;
+
+/// Synthesizes a button press/release from a trigger axis crossing thresholds,
+/// mirroring `GilrsBuilder::set_axis_to_btn`.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct AxisToButton {
+    button: Button,
+    up_threshold: f32,
+    down_threshold: f32,
+}
+
+impl Map {
+    /// Xbox layout is what [`Button`]/[`Axis`] already mirror, so this is the identity mapping.
+    pub fn xbox() -> Self {
+        Map::default()
+    }
+
+    /// PlayStation pads report East/South and North/West swapped relative to an Xbox pad.
+    pub fn playstation() -> Self {
+        Map {
+            button_map: HashMap::from([
+                (gilrs::Button::East, Button::South),
+                (gilrs::Button::South, Button::East),
+                (gilrs::Button::North, Button::West),
+                (gilrs::Button::West, Button::North),
+            ]),
+            ..Map::default()
+        }
+    }
+
+    pub fn button(&self, button: gilrs::Button) -> Button {
+        self.button_map
+            .get(&button)
+            .copied()
+            .unwrap_or_else(|| Button::from(button))
+    }
+
+    pub fn axis(&self, axis: gilrs::Axis) -> Axis {
+        self.axis_map
+            .get(&axis)
+            .copied()
+            .unwrap_or_else(|| Axis::from(axis))
+    }
+
+    pub fn axis_scale(&self, axis: Axis) -> f32 {
+        self.axis_value_map.get(&axis).copied().unwrap_or(1.0)
+    }
+
+    pub fn deadzone(&self, axis: Axis) -> f32 {
+        self.axis_deadzones
+            .get(&axis)
+            .copied()
+            .unwrap_or(DEFAULT_DEADZONE)
+    }
+
+    pub fn axis_to_button(&self, axis: Axis) -> Option<&AxisToButton> {
+        self.axis_to_button.get(&axis)
+    }
+
+    /// True if `button` is synthesized from a trigger axis, and therefore shouldn't
+    /// have its `button_pressed` state overwritten by the physical button's state.
+    pub fn is_axis_driven_button(&self, button: Button) -> bool {
+        self.axis_to_button.values().any(|cfg| cfg.button == button)
+    }
+}
+
 pub fn setup_tracing(verbosity_level: u8) {
     let filter = match verbosity_level {
         0 => tracing::level_filters::LevelFilter::WARN,
@@ -322,4 +729,173 @@ pub fn setup_tracing(verbosity_level: u8) {
 pub enum HopperRemoteError {
     #[error("Zenoh error {0:?}")]
     ZenohError(#[from] zenoh::Error),
+    #[error("Force feedback error {0:?}")]
+    ForceFeedbackError(#[from] gilrs::ff::Error),
+    #[error("No connected gamepad with id {0}")]
+    UnknownGamepad(usize),
+}
+
+/// A rumble/force-feedback command for a single gamepad, published onto `<topic>/rumble`.
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct RumbleCommand {
+    gamepad_id: usize,
+    strong_magnitude: u16,
+    weak_magnitude: u16,
+    duration_ms: u32,
+}
+
+/// Plays a [`RumbleCommand`] on its target gamepad, replacing any effect already running there.
+fn play_rumble(
+    gilrs: &Mutex<gilrs::Gilrs>,
+    active_effects: &Mutex<HashMap<usize, gilrs::ff::Effect>>,
+    command: &RumbleCommand,
+) -> Result<(), HopperRemoteError> {
+    let mut gilrs = gilrs.lock().unwrap();
+    // GamepadId can't be constructed directly, so find the gamepad whose id matches
+    // the wire id instead.
+    let gamepad_id = gilrs
+        .gamepads()
+        .find(|(id, _)| usize::from(*id) == command.gamepad_id)
+        .map(|(id, _)| id)
+        .ok_or(HopperRemoteError::UnknownGamepad(command.gamepad_id))?;
+
+    let effect = gilrs::ff::EffectBuilder::new()
+        .add_effect(gilrs::ff::BaseEffect {
+            kind: gilrs::ff::BaseEffectType::Strong {
+                magnitude: command.strong_magnitude,
+            },
+            scheduling: gilrs::ff::Replay {
+                play_for: gilrs::ff::Ticks::from_ms(command.duration_ms),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .add_effect(gilrs::ff::BaseEffect {
+            kind: gilrs::ff::BaseEffectType::Weak {
+                magnitude: command.weak_magnitude,
+            },
+            scheduling: gilrs::ff::Replay {
+                play_for: gilrs::ff::Ticks::from_ms(command.duration_ms),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .gamepads(&[gamepad_id])
+        .finish(&mut gilrs)?;
+    effect.play()?;
+
+    let mut active_effects = active_effects.lock().unwrap();
+    if let Some(previous) = active_effects.insert(command.gamepad_id, effect) {
+        let _ = previous.stop();
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deadzone_clamps_raw_value_before_scaling() {
+        assert_eq!(apply_deadzone_and_scale(0.05, 0.1, 2.0), 0.0);
+        assert_eq!(apply_deadzone_and_scale(0.2, 0.1, 2.0), 0.4);
+    }
+
+    #[test]
+    fn axis_to_button_presses_above_up_threshold_and_releases_below_down_threshold() {
+        let axis_to_button = AxisToButton {
+            button: Button::LeftTrigger2,
+            up_threshold: 0.5,
+            down_threshold: 0.1,
+        };
+        let mut gamepad_data = GamepadMessage::default();
+
+        synthesize_axis_button(&mut gamepad_data, Axis::LeftZ, 0.6, &axis_to_button);
+        assert_eq!(
+            gamepad_data.button_pressed.get(&Button::LeftTrigger2),
+            Some(&true)
+        );
+        assert_eq!(
+            gamepad_data.button_down_event_counter.get(&Button::LeftTrigger2),
+            Some(&1)
+        );
+
+        // Staying above the down threshold shouldn't re-trigger a press.
+        synthesize_axis_button(&mut gamepad_data, Axis::LeftZ, 0.7, &axis_to_button);
+        assert_eq!(
+            gamepad_data.button_down_event_counter.get(&Button::LeftTrigger2),
+            Some(&1)
+        );
+
+        synthesize_axis_button(&mut gamepad_data, Axis::LeftZ, 0.0, &axis_to_button);
+        assert_eq!(
+            gamepad_data.button_pressed.get(&Button::LeftTrigger2),
+            Some(&false)
+        );
+        assert_eq!(
+            gamepad_data.button_up_event_counter.get(&Button::LeftTrigger2),
+            Some(&1)
+        );
+    }
+
+    #[test]
+    fn playstation_map_swaps_east_south_and_north_west() {
+        let map = Map::playstation();
+        assert_eq!(map.button(gilrs::Button::East), Button::South);
+        assert_eq!(map.button(gilrs::Button::South), Button::East);
+        assert_eq!(map.button(gilrs::Button::North), Button::West);
+        assert_eq!(map.button(gilrs::Button::West), Button::North);
+        // Unmapped buttons fall back to the default conversion.
+        assert_eq!(map.button(gilrs::Button::Start), Button::Start);
+    }
+
+    #[test]
+    fn rumble_command_parses_from_json() {
+        let json = r#"{
+            "gamepad_id": 1,
+            "strong_magnitude": 30000,
+            "weak_magnitude": 15000,
+            "duration_ms": 250
+        }"#;
+        let command: RumbleCommand = serde_json::from_str(json).unwrap();
+        assert_eq!(command.gamepad_id, 1);
+        assert_eq!(command.strong_magnitude, 30000);
+        assert_eq!(command.weak_magnitude, 15000);
+        assert_eq!(command.duration_ms, 250);
+    }
+
+    #[test]
+    fn gamepad_changed_is_true_with_no_previous_state() {
+        assert!(gamepad_changed(None, &GamepadMessage::default(), 0.01));
+    }
+
+    #[test]
+    fn gamepad_changed_ignores_axis_deltas_within_epsilon() {
+        let mut previous = GamepadMessage::default();
+        previous.axis_state.insert(Axis::LeftStickX, 0.5);
+        let mut current = previous.clone();
+        current.axis_state.insert(Axis::LeftStickX, 0.505);
+
+        assert!(!gamepad_changed(Some(&previous), &current, 0.01));
+    }
+
+    #[test]
+    fn gamepad_changed_detects_axis_deltas_beyond_epsilon() {
+        let mut previous = GamepadMessage::default();
+        previous.axis_state.insert(Axis::LeftStickX, 0.5);
+        let mut current = previous.clone();
+        current.axis_state.insert(Axis::LeftStickX, 0.6);
+
+        assert!(gamepad_changed(Some(&previous), &current, 0.01));
+    }
+
+    #[test]
+    fn gamepad_changed_detects_button_pressed_flip() {
+        let previous = GamepadMessage::default();
+        let mut current = previous.clone();
+        current.button_pressed.insert(Button::South, true);
+
+        assert!(gamepad_changed(Some(&previous), &current, 0.01));
+    }
 }